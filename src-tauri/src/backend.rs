@@ -0,0 +1,463 @@
+use async_trait::async_trait;
+use futures::StreamExt;
+use ollama_rs::generation::chat::request::ChatMessageRequest;
+use ollama_rs::generation::chat::{ChatMessage, MessageRole};
+use ollama_rs::generation::options::GenerationOptions as OllamaModelOptions;
+use ollama_rs::Ollama;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tauri::{Emitter, Window};
+use tokio::sync::Mutex;
+
+/// Which provider a given chat request should be routed to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ValidModel {
+    Ollama,
+    OpenAI,
+    Anthropic,
+}
+
+/// Frontend-supplied configuration describing which backend to talk to and
+/// how. `base_url` and `api_key` are ignored by the `Ollama` backend.
+#[derive(Debug, Clone, Deserialize)]
+pub struct BackendConfig {
+    pub backend: ValidModel,
+    pub model: String,
+    #[serde(default)]
+    pub base_url: Option<String>,
+    #[serde(default)]
+    pub api_key: Option<String>,
+    #[serde(default)]
+    pub options: Option<GenerationOptions>,
+    /// Client-side throttle for this backend; `None` means unlimited, which
+    /// is the right default for a local Ollama install.
+    #[serde(default)]
+    pub max_requests_per_second: Option<f64>,
+}
+
+/// Frontend-tunable generation parameters, applied on top of whatever
+/// defaults the target backend normally uses.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct GenerationOptions {
+    #[serde(default)]
+    pub system: Option<String>,
+    #[serde(default)]
+    pub temperature: Option<f32>,
+    #[serde(default)]
+    pub top_p: Option<f32>,
+    #[serde(default)]
+    pub num_ctx: Option<u64>,
+    #[serde(default)]
+    pub seed: Option<i32>,
+    #[serde(default)]
+    pub stop: Option<Vec<String>>,
+}
+
+/// A provider capable of turning a history + prompt into an assistant reply.
+/// Implementations own their own request shape; callers only deal in
+/// `ChatMessage`s so the rest of the app stays backend-agnostic.
+#[async_trait]
+pub trait ChatBackend {
+    async fn send(&self, history: Vec<ChatMessage>, prompt: ChatMessage) -> Result<String, String>;
+
+    /// Streaming variant of `send`, emitting `llm-token` events as content
+    /// arrives. Backends that can't stream natively fall back to one big
+    /// token emitted after the full reply comes back.
+    async fn send_stream(
+        &self,
+        window: &Window,
+        history: Vec<ChatMessage>,
+        prompt: ChatMessage,
+    ) -> Result<String, String> {
+        let content = self.send(history, prompt).await?;
+        window
+            .emit("llm-token", &content)
+            .map_err(|e| format!("Failed to emit llm-token: {}", e))?;
+        Ok(content)
+    }
+}
+
+pub struct OllamaBackend {
+    ollama: Ollama,
+    model: String,
+    options: Option<GenerationOptions>,
+}
+
+impl OllamaBackend {
+    pub fn new(ollama: Ollama, model: String, options: Option<GenerationOptions>) -> Self {
+        Self {
+            ollama,
+            model,
+            options,
+        }
+    }
+
+    /// Apply the system prompt and `GenerationOptions` shared by `send` and
+    /// `send_stream`, returning the history (with system message prepended,
+    /// if any) and the request built from the remaining options.
+    fn prepare(
+        &self,
+        mut history: Vec<ChatMessage>,
+        prompt: ChatMessage,
+    ) -> (Vec<ChatMessage>, ChatMessageRequest) {
+        if let Some(system) = self.options.as_ref().and_then(|o| o.system.clone()) {
+            history.insert(0, ChatMessage::new(MessageRole::System, system));
+        }
+
+        let mut req = ChatMessageRequest::new(self.model.clone(), vec![prompt]);
+        if let Some(opts) = &self.options {
+            let mut model_options = OllamaModelOptions::default();
+            if let Some(temperature) = opts.temperature {
+                model_options = model_options.temperature(temperature);
+            }
+            if let Some(top_p) = opts.top_p {
+                model_options = model_options.top_p(top_p);
+            }
+            if let Some(num_ctx) = opts.num_ctx {
+                model_options = model_options.num_ctx(num_ctx);
+            }
+            if let Some(seed) = opts.seed {
+                model_options = model_options.seed(seed);
+            }
+            if let Some(stop) = opts.stop.clone() {
+                model_options = model_options.stop(stop);
+            }
+            req = req.options(model_options);
+        }
+
+        (history, req)
+    }
+}
+
+#[async_trait]
+impl ChatBackend for OllamaBackend {
+    async fn send(&self, history: Vec<ChatMessage>, prompt: ChatMessage) -> Result<String, String> {
+        let (mut history, req) = self.prepare(history, prompt);
+
+        self.ollama
+            .send_chat_messages_with_history(&mut history, req)
+            .await
+            .map(|res| res.message.content)
+            .map_err(|e| format!("Error communicating with Ollama: {}", e))
+    }
+
+    async fn send_stream(
+        &self,
+        window: &Window,
+        history: Vec<ChatMessage>,
+        prompt: ChatMessage,
+    ) -> Result<String, String> {
+        let (history, req) = self.prepare(history, prompt);
+        let history = Arc::new(Mutex::new(history));
+
+        let mut stream = self
+            .ollama
+            .send_chat_messages_with_history_stream(history, req)
+            .await
+            .map_err(|e| format!("Error communicating with Ollama: {}", e))?;
+
+        let mut assembled = String::new();
+        while let Some(chunk) = stream.next().await {
+            let response = chunk.map_err(|e| format!("Error streaming from Ollama: {}", e))?;
+            assembled.push_str(&response.message.content);
+            window
+                .emit("llm-token", &response.message.content)
+                .map_err(|e| format!("Failed to emit llm-token: {}", e))?;
+        }
+
+        Ok(assembled)
+    }
+}
+
+/// Shared request/response shapes for the OpenAI-compatible `/v1/chat/completions` API.
+#[derive(Serialize)]
+struct OpenAiRequestMessage {
+    role: String,
+    content: String,
+}
+
+#[derive(Serialize)]
+struct OpenAiRequest {
+    model: String,
+    messages: Vec<OpenAiRequestMessage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop: Option<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+struct OpenAiChoice {
+    message: OpenAiResponseMessage,
+}
+
+#[derive(Deserialize)]
+struct OpenAiResponseMessage {
+    content: String,
+}
+
+#[derive(Deserialize)]
+struct OpenAiResponse {
+    choices: Vec<OpenAiChoice>,
+}
+
+fn role_to_str(role: &MessageRole) -> &'static str {
+    match role {
+        MessageRole::User => "user",
+        MessageRole::Assistant => "assistant",
+        MessageRole::System => "system",
+        MessageRole::Tool => "tool",
+    }
+}
+
+/// Shape shared by OpenAI's and Anthropic's error responses:
+/// `{"error": {"message": "..."}}`.
+#[derive(Deserialize)]
+struct ApiErrorBody {
+    error: ApiErrorDetail,
+}
+
+#[derive(Deserialize)]
+struct ApiErrorDetail {
+    message: String,
+}
+
+/// Turn a non-2xx response body into a message that actually explains what
+/// went wrong (bad key, bad model, quota, ...), falling back to the raw body
+/// if it's not the `error.message` shape we expect.
+fn describe_error_response(provider: &str, status: reqwest::StatusCode, body: &str) -> String {
+    let detail = serde_json::from_str::<ApiErrorBody>(body)
+        .map(|b| b.error.message)
+        .unwrap_or_else(|_| body.to_string());
+    format!("{} returned {}: {}", provider, status, detail)
+}
+
+pub struct OpenAiBackend {
+    model: String,
+    base_url: String,
+    api_key: String,
+    options: Option<GenerationOptions>,
+}
+
+impl OpenAiBackend {
+    pub fn new(
+        model: String,
+        base_url: Option<String>,
+        api_key: String,
+        options: Option<GenerationOptions>,
+    ) -> Self {
+        Self {
+            model,
+            base_url: base_url.unwrap_or_else(|| "https://api.openai.com".to_string()),
+            api_key,
+            options,
+        }
+    }
+}
+
+#[async_trait]
+impl ChatBackend for OpenAiBackend {
+    async fn send(&self, mut history: Vec<ChatMessage>, prompt: ChatMessage) -> Result<String, String> {
+        if let Some(system) = self.options.as_ref().and_then(|o| o.system.clone()) {
+            history.insert(0, ChatMessage::new(MessageRole::System, system));
+        }
+
+        let messages = history
+            .iter()
+            .chain(std::iter::once(&prompt))
+            .map(|m| OpenAiRequestMessage {
+                role: role_to_str(&m.role).to_string(),
+                content: m.content.clone(),
+            })
+            .collect();
+
+        let client = reqwest::Client::new();
+        let res = client
+            .post(format!("{}/v1/chat/completions", self.base_url))
+            .bearer_auth(&self.api_key)
+            .json(&OpenAiRequest {
+                model: self.model.clone(),
+                messages,
+                temperature: self.options.as_ref().and_then(|o| o.temperature),
+                top_p: self.options.as_ref().and_then(|o| o.top_p),
+                seed: self.options.as_ref().and_then(|o| o.seed),
+                stop: self.options.as_ref().and_then(|o| o.stop.clone()),
+            })
+            .send()
+            .await
+            .map_err(|e| format!("Error communicating with OpenAI: {}", e))?;
+
+        let status = res.status();
+        let text = res
+            .text()
+            .await
+            .map_err(|e| format!("Failed to read OpenAI response: {}", e))?;
+
+        if !status.is_success() {
+            return Err(describe_error_response("OpenAI", status, &text));
+        }
+
+        let body: OpenAiResponse = serde_json::from_str(&text)
+            .map_err(|e| format!("Failed to parse OpenAI response: {}", e))?;
+
+        body.choices
+            .into_iter()
+            .next()
+            .map(|c| c.message.content)
+            .ok_or_else(|| "OpenAI response contained no choices".to_string())
+    }
+}
+
+/// Shared request/response shapes for the Anthropic Messages API.
+#[derive(Serialize)]
+struct AnthropicRequestMessage {
+    role: String,
+    content: String,
+}
+
+#[derive(Serialize)]
+struct AnthropicRequest {
+    model: String,
+    max_tokens: u32,
+    messages: Vec<AnthropicRequestMessage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop_sequences: Option<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+struct AnthropicContentBlock {
+    text: String,
+}
+
+#[derive(Deserialize)]
+struct AnthropicResponse {
+    content: Vec<AnthropicContentBlock>,
+}
+
+pub struct AnthropicBackend {
+    model: String,
+    base_url: String,
+    api_key: String,
+    options: Option<GenerationOptions>,
+}
+
+impl AnthropicBackend {
+    pub fn new(
+        model: String,
+        base_url: Option<String>,
+        api_key: String,
+        options: Option<GenerationOptions>,
+    ) -> Self {
+        Self {
+            model,
+            base_url: base_url.unwrap_or_else(|| "https://api.anthropic.com".to_string()),
+            api_key,
+            options,
+        }
+    }
+}
+
+#[async_trait]
+impl ChatBackend for AnthropicBackend {
+    async fn send(&self, history: Vec<ChatMessage>, prompt: ChatMessage) -> Result<String, String> {
+        // Anthropic takes the system prompt as a dedicated top-level field
+        // rather than a message with a "system" role.
+        let messages = history
+            .iter()
+            .chain(std::iter::once(&prompt))
+            .filter(|m| m.role != MessageRole::System)
+            .map(|m| AnthropicRequestMessage {
+                role: role_to_str(&m.role).to_string(),
+                content: m.content.clone(),
+            })
+            .collect();
+
+        let client = reqwest::Client::new();
+        let res = client
+            .post(format!("{}/v1/messages", self.base_url))
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", "2023-06-01")
+            .json(&AnthropicRequest {
+                model: self.model.clone(),
+                max_tokens: 1024,
+                messages,
+                system: self.options.as_ref().and_then(|o| o.system.clone()),
+                temperature: self.options.as_ref().and_then(|o| o.temperature),
+                top_p: self.options.as_ref().and_then(|o| o.top_p),
+                stop_sequences: self.options.as_ref().and_then(|o| o.stop.clone()),
+            })
+            .send()
+            .await
+            .map_err(|e| format!("Error communicating with Anthropic: {}", e))?;
+
+        let status = res.status();
+        let text = res
+            .text()
+            .await
+            .map_err(|e| format!("Failed to read Anthropic response: {}", e))?;
+
+        if !status.is_success() {
+            return Err(describe_error_response("Anthropic", status, &text));
+        }
+
+        let body: AnthropicResponse = serde_json::from_str(&text)
+            .map_err(|e| format!("Failed to parse Anthropic response: {}", e))?;
+
+        body.content
+            .into_iter()
+            .next()
+            .map(|c| c.text)
+            .ok_or_else(|| "Anthropic response contained no content".to_string())
+    }
+}
+
+/// Build the concrete backend described by `config`, returning an error for
+/// cloud backends missing the API key they need. `ollama` is the client for
+/// the currently configured Ollama endpoint (see `settings::EndpointState`).
+pub fn build_backend(
+    config: &BackendConfig,
+    ollama: Ollama,
+) -> Result<Box<dyn ChatBackend + Send + Sync>, String> {
+    match config.backend {
+        ValidModel::Ollama => Ok(Box::new(OllamaBackend::new(
+            ollama,
+            config.model.clone(),
+            config.options.clone(),
+        ))),
+        ValidModel::OpenAI => {
+            let api_key = config
+                .api_key
+                .clone()
+                .ok_or_else(|| "OpenAI backend requires an api_key".to_string())?;
+            Ok(Box::new(OpenAiBackend::new(
+                config.model.clone(),
+                config.base_url.clone(),
+                api_key,
+                config.options.clone(),
+            )))
+        }
+        ValidModel::Anthropic => {
+            let api_key = config
+                .api_key
+                .clone()
+                .ok_or_else(|| "Anthropic backend requires an api_key".to_string())?;
+            Ok(Box::new(AnthropicBackend::new(
+                config.model.clone(),
+                config.base_url.clone(),
+                api_key,
+                config.options.clone(),
+            )))
+        }
+    }
+}
@@ -0,0 +1,202 @@
+use serde::Serialize;
+use sqlx::sqlite::SqlitePoolOptions;
+use sqlx::{Executor, Row, SqlitePool};
+use std::path::Path;
+
+/// Owns the connection pool backing the conversation store; managed as Tauri state.
+pub struct Db(pub SqlitePool);
+
+#[derive(Serialize, Clone, Debug)]
+pub struct Conversation {
+    pub id: i64,
+    pub title: String,
+    pub created_at: String,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct StoredMessage {
+    pub id: i64,
+    pub conversation_id: i64,
+    pub role: String,
+    pub content: String,
+    pub model: Option<String>,
+    pub created_at: String,
+}
+
+/// Open (creating if needed) the conversation database under `app_data_dir`
+/// and make sure the `conversations`/`messages` tables exist.
+pub async fn init(app_data_dir: &Path) -> Result<SqlitePool, String> {
+    std::fs::create_dir_all(app_data_dir)
+        .map_err(|e| format!("Failed to create app data dir: {}", e))?;
+    let db_path = app_data_dir.join("conversations.sqlite");
+    let url = format!("sqlite://{}?mode=rwc", db_path.display());
+
+    let pool = SqlitePoolOptions::new()
+        .max_connections(5)
+        .after_connect(|conn, _meta| {
+            Box::pin(async move {
+                conn.execute("PRAGMA foreign_keys = ON;").await?;
+                Ok(())
+            })
+        })
+        .connect(&url)
+        .await
+        .map_err(|e| format!("Failed to open conversation database: {}", e))?;
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS conversations (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            title TEXT NOT NULL,
+            created_at TEXT NOT NULL DEFAULT (datetime('now'))
+        )",
+    )
+    .execute(&pool)
+    .await
+    .map_err(|e| format!("Failed to create conversations table: {}", e))?;
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS messages (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            conversation_id INTEGER NOT NULL REFERENCES conversations(id) ON DELETE CASCADE,
+            role TEXT NOT NULL,
+            content TEXT NOT NULL,
+            model TEXT,
+            created_at TEXT NOT NULL DEFAULT (datetime('now'))
+        )",
+    )
+    .execute(&pool)
+    .await
+    .map_err(|e| format!("Failed to create messages table: {}", e))?;
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS settings (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL
+        )",
+    )
+    .execute(&pool)
+    .await
+    .map_err(|e| format!("Failed to create settings table: {}", e))?;
+
+    Ok(pool)
+}
+
+pub async fn get_setting(pool: &SqlitePool, key: &str) -> Result<Option<String>, String> {
+    sqlx::query("SELECT value FROM settings WHERE key = ?")
+        .bind(key)
+        .fetch_optional(pool)
+        .await
+        .map(|row| row.map(|r| r.get("value")))
+        .map_err(|e| format!("Failed to read setting '{}': {}", key, e))
+}
+
+pub async fn set_setting(pool: &SqlitePool, key: &str, value: &str) -> Result<(), String> {
+    sqlx::query(
+        "INSERT INTO settings (key, value) VALUES (?, ?) \
+         ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+    )
+    .bind(key)
+    .bind(value)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to store setting '{}': {}", key, e))?;
+    Ok(())
+}
+
+pub async fn create_conversation(pool: &SqlitePool, title: &str) -> Result<i64, String> {
+    let rec = sqlx::query("INSERT INTO conversations (title) VALUES (?) RETURNING id")
+        .bind(title)
+        .fetch_one(pool)
+        .await
+        .map_err(|e| format!("Failed to create conversation: {}", e))?;
+    Ok(rec.get("id"))
+}
+
+pub async fn list_conversations(pool: &SqlitePool) -> Result<Vec<Conversation>, String> {
+    sqlx::query_as::<_, (i64, String, String)>(
+        "SELECT id, title, created_at FROM conversations ORDER BY created_at DESC",
+    )
+    .fetch_all(pool)
+    .await
+    .map(|rows| {
+        rows.into_iter()
+            .map(|(id, title, created_at)| Conversation {
+                id,
+                title,
+                created_at,
+            })
+            .collect()
+    })
+    .map_err(|e| format!("Failed to list conversations: {}", e))
+}
+
+pub async fn load_messages(pool: &SqlitePool, conversation_id: i64) -> Result<Vec<StoredMessage>, String> {
+    sqlx::query_as::<_, (i64, i64, String, String, Option<String>, String)>(
+        "SELECT id, conversation_id, role, content, model, created_at FROM messages \
+         WHERE conversation_id = ? ORDER BY id ASC",
+    )
+    .bind(conversation_id)
+    .fetch_all(pool)
+    .await
+    .map(|rows| {
+        rows.into_iter()
+            .map(
+                |(id, conversation_id, role, content, model, created_at)| StoredMessage {
+                    id,
+                    conversation_id,
+                    role,
+                    content,
+                    model,
+                    created_at,
+                },
+            )
+            .collect()
+    })
+    .map_err(|e| format!("Failed to load conversation messages: {}", e))
+}
+
+/// Append the user prompt and assistant reply for one exchange as a single
+/// transaction, so a mid-write failure can never strand a user turn without
+/// its matching reply.
+pub async fn append_exchange(
+    pool: &SqlitePool,
+    conversation_id: i64,
+    user_content: &str,
+    assistant_content: &str,
+    model: Option<&str>,
+) -> Result<(), String> {
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    sqlx::query("INSERT INTO messages (conversation_id, role, content, model) VALUES (?, 'user', ?, ?)")
+        .bind(conversation_id)
+        .bind(user_content)
+        .bind(model)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to append user message: {}", e))?;
+
+    sqlx::query("INSERT INTO messages (conversation_id, role, content, model) VALUES (?, 'assistant', ?, ?)")
+        .bind(conversation_id)
+        .bind(assistant_content)
+        .bind(model)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to append assistant message: {}", e))?;
+
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to commit conversation update: {}", e))?;
+    Ok(())
+}
+
+pub async fn delete_conversation(pool: &SqlitePool, conversation_id: i64) -> Result<(), String> {
+    sqlx::query("DELETE FROM conversations WHERE id = ?")
+        .bind(conversation_id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to delete conversation: {}", e))?;
+    Ok(())
+}
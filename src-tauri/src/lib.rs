@@ -1,7 +1,17 @@
-use ollama_rs::generation::chat::request::ChatMessageRequest;
+mod backend;
+mod db;
+mod rate_limit;
+mod settings;
+
+use backend::{build_backend, BackendConfig};
+use db::{Conversation, Db, StoredMessage};
+use futures::StreamExt;
 use ollama_rs::generation::chat::{ChatMessage, MessageRole};
-use ollama_rs::Ollama;
+use rate_limit::RateLimiterState;
 use serde::{Deserialize, Serialize};
+use settings::{ConnectionStatus, EndpointState, OllamaEndpoint};
+use tauri::{Emitter, Manager, State, Window};
+use tokio::sync::Mutex;
 
 // --- Struct matching the frontend's ChatMessageUI ---
 // Used to receive messages from the frontend
@@ -17,14 +27,42 @@ struct SerializableModel {
     name: String,
     modified_at: String,
     size: u64,
+    digest: String,
+}
+
+// Progress update emitted to the frontend while a model download is in flight.
+#[derive(Serialize, Clone, Debug)]
+struct PullProgress {
+    status: String,
+    digest: Option<String>,
+    total: Option<u64>,
+    completed: Option<u64>,
+}
+
+// Subset of ollama-rs's model details worth surfacing to the frontend.
+#[derive(Serialize, Clone, Debug)]
+struct ModelInfo {
+    modelfile: String,
+    parameters: String,
+    template: String,
+    family: String,
+    parameter_size: String,
+    quantization_level: String,
 }
 
 #[tauri::command]
-async fn ask_llm(messages: Vec<FrontendMessage>, model: String) -> Result<String, String> {
-    let mut ollama = Ollama::default();
+async fn ask_llm(
+    db: State<'_, Db>,
+    endpoint: State<'_, EndpointState>,
+    limiter: State<'_, RateLimiterState>,
+    messages: Vec<FrontendMessage>,
+    config: BackendConfig,
+    conversation_id: Option<i64>,
+) -> Result<String, String> {
     println!(
-        "Asking LLM (Rust backend) using function from docs for model '{}' with {} total messages received",
-        model,
+        "Asking LLM (Rust backend) via {:?} backend, model '{}' with {} total messages received",
+        config.backend,
+        config.model,
         messages.len()
     );
 
@@ -42,55 +80,191 @@ async fn ask_llm(messages: Vec<FrontendMessage>, model: String) -> Result<String
         })
         .collect();
 
-    // Separate history from the last message (the prompt)
     // Ensure there's at least one message to act as the prompt
     if all_chat_messages.is_empty() {
         return Err("No messages provided to LLM.".to_string());
     }
 
     // The last message is the new prompt for the request
-    // We need to clone it as the request takes ownership
     let last_message = all_chat_messages.last().unwrap().clone();
 
-    // The rest of the messages form the initial history
-    // The library will mutate this history vector
-    let mut history: Vec<ChatMessage> = all_chat_messages.into_iter().rev().skip(1).rev().collect(); // Efficiently get all but last
+    // History is reconstructed from the durable conversation store, if any,
+    // rather than trusting whatever the frontend happened to resend.
+    let history: Vec<ChatMessage> = match conversation_id {
+        Some(id) => db::load_messages(&db.0, id)
+            .await?
+            .into_iter()
+            .map(|m| {
+                let role = match m.role.as_str() {
+                    "assistant" => MessageRole::Assistant,
+                    "system" => MessageRole::System,
+                    _ => MessageRole::User,
+                };
+                ChatMessage::new(role, m.content)
+            })
+            .collect(),
+        None => Vec::new(),
+    };
 
     println!(
-        "Extracted history size: {}, Prompt: '{}'",
+        "Loaded history size: {}, Prompt: '{}'",
         history.len(),
         last_message.content
     );
 
-    // Create the request with ONLY the last message
-    let req = ChatMessageRequest::new(model.clone(), vec![last_message]);
-
-    // Call the function from the docs example
-    let res = ollama
-        .send_chat_messages_with_history(&mut history, req)
-        .await;
-
-    // --- MODIFIED RESPONSE HANDLING (Direct Access matching Docs Example) ---
-    match res {
-        Ok(response) => {
-            // Directly access .message.content as shown in the example
-            // This assumes that if the overall Result is Ok, response.message is guaranteed to exist
-            // and is NOT an Option based on compiler errors and the example.
-            println!("Ollama responded successfully.");
-            // Note: The example uses println!, we need to return Ok(content)
-            Ok(response.message.content) // <-- Direct access
+    let rate_limit_key = format!("{:?}:{}", config.backend, config.base_url.as_deref().unwrap_or(""));
+    rate_limit::throttle(&limiter, &rate_limit_key, config.max_requests_per_second).await;
+
+    let ollama_client = endpoint.0.lock().await.client();
+    let chat_backend = build_backend(&config, ollama_client)?;
+    let content = chat_backend.send(history, last_message.clone()).await?;
+    println!("{:?} backend responded successfully.", config.backend);
+
+    // The model call already succeeded by this point, so a persistence
+    // failure shouldn't discard the reply from the caller's perspective —
+    // log it and still return the content the user asked for.
+    if let Some(id) = conversation_id {
+        if let Err(e) = db::append_exchange(
+            &db.0,
+            id,
+            &last_message.content,
+            &content,
+            Some(&config.model),
+        )
+        .await
+        {
+            eprintln!("Failed to persist conversation {}: {}", id, e);
         }
-        Err(e) => {
-            eprintln!("Ollama API error: {}", e);
-            Err(format!("Error communicating with Ollama: {}", e))
+    }
+
+    Ok(content)
+}
+
+#[tauri::command]
+async fn create_conversation(db: State<'_, Db>, title: String) -> Result<i64, String> {
+    db::create_conversation(&db.0, &title).await
+}
+
+#[tauri::command]
+async fn list_conversations(db: State<'_, Db>) -> Result<Vec<Conversation>, String> {
+    db::list_conversations(&db.0).await
+}
+
+#[tauri::command]
+async fn load_conversation(db: State<'_, Db>, id: i64) -> Result<Vec<StoredMessage>, String> {
+    db::load_messages(&db.0, id).await
+}
+
+#[tauri::command]
+async fn delete_conversation(db: State<'_, Db>, id: i64) -> Result<(), String> {
+    db::delete_conversation(&db.0, id).await
+}
+
+// Payload emitted once the stream has finished, carrying the assembled
+// message so the frontend can persist it without reassembling tokens itself.
+#[derive(Serialize, Clone, Debug)]
+struct LlmStreamDone {
+    content: String,
+}
+
+#[tauri::command]
+async fn ask_llm_stream(
+    window: Window,
+    db: State<'_, Db>,
+    endpoint: State<'_, EndpointState>,
+    limiter: State<'_, RateLimiterState>,
+    messages: Vec<FrontendMessage>,
+    config: BackendConfig,
+    conversation_id: Option<i64>,
+) -> Result<(), String> {
+    println!(
+        "Asking LLM (Rust backend, streaming) via {:?} backend, model '{}' with {} total messages received",
+        config.backend,
+        config.model,
+        messages.len()
+    );
+
+    // Map all incoming messages
+    let all_chat_messages: Vec<ChatMessage> = messages
+        .into_iter()
+        .map(|msg| {
+            let role = match msg.role.as_str() {
+                "user" => MessageRole::User,
+                "assistant" => MessageRole::Assistant,
+                "system" => MessageRole::System,
+                _ => MessageRole::User,
+            };
+            ChatMessage::new(role, msg.content)
+        })
+        .collect();
+
+    if all_chat_messages.is_empty() {
+        return Err("No messages provided to LLM.".to_string());
+    }
+
+    // The last message is the new prompt for the request
+    let last_message = all_chat_messages.last().unwrap().clone();
+
+    // History is reconstructed from the durable conversation store, if any,
+    // rather than trusting whatever the frontend happened to resend.
+    let history: Vec<ChatMessage> = match conversation_id {
+        Some(id) => db::load_messages(&db.0, id)
+            .await?
+            .into_iter()
+            .map(|m| {
+                let role = match m.role.as_str() {
+                    "assistant" => MessageRole::Assistant,
+                    "system" => MessageRole::System,
+                    _ => MessageRole::User,
+                };
+                ChatMessage::new(role, m.content)
+            })
+            .collect(),
+        None => Vec::new(),
+    };
+
+    let rate_limit_key = format!("{:?}:{}", config.backend, config.base_url.as_deref().unwrap_or(""));
+    rate_limit::throttle(&limiter, &rate_limit_key, config.max_requests_per_second).await;
+
+    let ollama_client = endpoint.0.lock().await.client();
+    let chat_backend = build_backend(&config, ollama_client)?;
+    let assembled = chat_backend
+        .send_stream(&window, history, last_message.clone())
+        .await?;
+    println!("{:?} backend finished streaming.", config.backend);
+
+    window
+        .emit(
+            "llm-done",
+            LlmStreamDone {
+                content: assembled.clone(),
+            },
+        )
+        .map_err(|e| format!("Failed to emit llm-done: {}", e))?;
+
+    // The model call already succeeded by this point, so a persistence
+    // failure shouldn't be treated as the request failing — log it and
+    // leave the frontend with the reply it already received.
+    if let Some(id) = conversation_id {
+        if let Err(e) = db::append_exchange(
+            &db.0,
+            id,
+            &last_message.content,
+            &assembled,
+            Some(&config.model),
+        )
+        .await
+        {
+            eprintln!("Failed to persist conversation {}: {}", id, e);
         }
     }
-    // --- END MODIFIED RESPONSE HANDLING ---
+
+    Ok(())
 }
 
 #[tauri::command]
-async fn get_ollama_models() -> Result<Vec<SerializableModel>, String> {
-    let ollama = Ollama::default();
+async fn get_ollama_models(endpoint: State<'_, EndpointState>) -> Result<Vec<SerializableModel>, String> {
+    let ollama = endpoint.0.lock().await.client();
     match ollama.list_local_models().await {
         Ok(models) => {
             // Map to serializable struct
@@ -100,6 +274,7 @@ async fn get_ollama_models() -> Result<Vec<SerializableModel>, String> {
                     name: m.name,
                     modified_at: m.modified_at,
                     size: m.size,
+                    digest: m.digest,
                 })
                 .collect();
             Ok(serializable_models)
@@ -111,12 +286,149 @@ async fn get_ollama_models() -> Result<Vec<SerializableModel>, String> {
     }
 }
 
+#[tauri::command]
+async fn pull_model(
+    window: Window,
+    endpoint: State<'_, EndpointState>,
+    name: String,
+) -> Result<(), String> {
+    let ollama = endpoint.0.lock().await.client();
+    let mut stream = ollama
+        .pull_model_stream(name.clone(), false)
+        .await
+        .map_err(|e| format!("Failed to start pulling model '{}': {}", name, e))?;
+
+    while let Some(update) = stream.next().await {
+        match update {
+            Ok(status) => {
+                window
+                    .emit(
+                        "model-pull-progress",
+                        PullProgress {
+                            status: status.message,
+                            digest: status.digest,
+                            total: status.total,
+                            completed: status.completed,
+                        },
+                    )
+                    .map_err(|e| format!("Failed to emit model-pull-progress: {}", e))?;
+            }
+            Err(e) => {
+                eprintln!("Error pulling model '{}': {}", name, e);
+                return Err(format!("Error pulling model '{}': {}", name, e));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+async fn delete_model(endpoint: State<'_, EndpointState>, name: String) -> Result<(), String> {
+    let ollama = endpoint.0.lock().await.client();
+    ollama
+        .delete_model(name.clone())
+        .await
+        .map_err(|e| format!("Failed to delete model '{}': {}", name, e))
+}
+
+#[tauri::command]
+async fn model_info(endpoint: State<'_, EndpointState>, name: String) -> Result<ModelInfo, String> {
+    let ollama = endpoint.0.lock().await.client();
+    let info = ollama
+        .show_model_info(name.clone())
+        .await
+        .map_err(|e| format!("Failed to fetch info for model '{}': {}", name, e))?;
+
+    Ok(ModelInfo {
+        modelfile: info.modelfile,
+        parameters: info.parameters,
+        template: info.template,
+        family: info.details.family,
+        parameter_size: info.details.parameter_size,
+        quantization_level: info.details.quantization_level,
+    })
+}
+
+#[tauri::command]
+async fn set_ollama_endpoint(
+    db: State<'_, Db>,
+    endpoint: State<'_, EndpointState>,
+    host: String,
+    port: u16,
+) -> Result<ConnectionStatus, String> {
+    if host.trim().is_empty() {
+        return Err("Host must not be empty.".to_string());
+    }
+    if port == 0 {
+        return Err("Port must not be zero.".to_string());
+    }
+
+    let candidate = OllamaEndpoint { host, port };
+    let status = settings::probe(&candidate).await;
+    if !status.available {
+        return Err(format!(
+            "Could not reach Ollama at {}",
+            candidate.base_url()
+        ));
+    }
+
+    db::set_setting(&db.0, "ollama_host", &candidate.host).await?;
+    db::set_setting(&db.0, "ollama_port", &candidate.port.to_string()).await?;
+    *endpoint.0.lock().await = candidate;
+
+    Ok(status)
+}
+
+#[tauri::command]
+async fn check_connection(endpoint: State<'_, EndpointState>) -> Result<ConnectionStatus, String> {
+    let current = endpoint.0.lock().await.clone();
+    Ok(settings::probe(&current).await)
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_sql::Builder::default().build())
         .plugin(tauri_plugin_opener::init())
-        .invoke_handler(tauri::generate_handler![get_ollama_models, ask_llm])
+        .setup(|app| {
+            let app_data_dir = app.path().app_data_dir()?;
+            let pool = tauri::async_runtime::block_on(db::init(&app_data_dir))
+                .map_err(|e| -> Box<dyn std::error::Error> { e.into() })?;
+
+            let mut endpoint = OllamaEndpoint::default();
+            tauri::async_runtime::block_on(async {
+                if let Some(host) = db::get_setting(&pool, "ollama_host").await? {
+                    endpoint.host = host;
+                }
+                if let Some(port) = db::get_setting(&pool, "ollama_port").await? {
+                    endpoint.port = port
+                        .parse()
+                        .map_err(|e| format!("Stored ollama_port is not a valid port: {}", e))?;
+                }
+                Ok::<(), String>(())
+            })
+            .map_err(|e| -> Box<dyn std::error::Error> { e.into() })?;
+
+            app.manage(Db(pool));
+            app.manage(EndpointState(Mutex::new(endpoint)));
+            app.manage(RateLimiterState::default());
+            Ok(())
+        })
+        .invoke_handler(tauri::generate_handler![
+            get_ollama_models,
+            ask_llm,
+            ask_llm_stream,
+            create_conversation,
+            list_conversations,
+            load_conversation,
+            delete_conversation,
+            pull_model,
+            delete_model,
+            model_info,
+            set_ollama_endpoint,
+            check_connection
+        ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
@@ -0,0 +1,41 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use tokio::time::sleep;
+
+/// Last-dispatch timestamp per throttled key (typically a backend+base URL
+/// pair), guarded by a single mutex since contention here is negligible.
+#[derive(Default)]
+pub struct RateLimiterState(pub Mutex<HashMap<String, Instant>>);
+
+/// Token-bucket of size 1: if `key`'s last dispatch was less than
+/// `1 / max_requests_per_second` ago, sleep out the remainder before
+/// returning. A `None` or non-positive rate disables throttling entirely,
+/// which is how local Ollama stays unlimited while cloud backends are capped.
+pub async fn throttle(state: &RateLimiterState, key: &str, max_requests_per_second: Option<f64>) {
+    let Some(rate) = max_requests_per_second.filter(|r| *r > 0.0) else {
+        return;
+    };
+    let min_interval = Duration::from_secs_f64(1.0 / rate);
+
+    // Reserve this call's slot atomically: read the previous dispatch time
+    // and write the new one back under the same lock acquisition, so two
+    // concurrent callers for the same key can never both compute the same
+    // delay. Only the sleep itself happens after the guard is dropped, so a
+    // sleeping caller still doesn't block other keys from checking in.
+    let now = Instant::now();
+    let dispatch_time = {
+        let mut guard = state.0.lock().await;
+        let dispatch_time = guard
+            .get(key)
+            .map(|previous| (*previous + min_interval).max(now))
+            .unwrap_or(now);
+        guard.insert(key.to_string(), dispatch_time);
+        dispatch_time
+    };
+
+    let delay = dispatch_time.duration_since(now);
+    if delay > Duration::ZERO {
+        sleep(delay).await;
+    }
+}
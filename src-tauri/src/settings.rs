@@ -0,0 +1,71 @@
+use ollama_rs::Ollama;
+use serde::Serialize;
+use tokio::sync::Mutex;
+
+/// Where to reach the Ollama server. Defaults to the usual local install;
+/// overridden by `set_ollama_endpoint` for remote or non-default setups.
+#[derive(Debug, Clone)]
+pub struct OllamaEndpoint {
+    pub host: String,
+    pub port: u16,
+}
+
+impl Default for OllamaEndpoint {
+    fn default() -> Self {
+        Self {
+            host: "http://localhost".to_string(),
+            port: 11434,
+        }
+    }
+}
+
+impl OllamaEndpoint {
+    pub fn client(&self) -> Ollama {
+        Ollama::new(self.host.clone(), self.port)
+    }
+
+    pub fn base_url(&self) -> String {
+        format!("{}:{}", self.host, self.port)
+    }
+}
+
+/// Shared, mutable handle to the currently configured endpoint.
+pub struct EndpointState(pub Mutex<OllamaEndpoint>);
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ConnectionStatus {
+    pub available: bool,
+    pub version: Option<String>,
+}
+
+/// Ping `endpoint`'s `/api/version` to check it's reachable; used by both
+/// `set_ollama_endpoint` and `check_connection`. Any HTTP response counts as
+/// available; `version` is only set when the body parses as a version reply.
+pub async fn probe(endpoint: &OllamaEndpoint) -> ConnectionStatus {
+    #[derive(serde::Deserialize)]
+    struct VersionResponse {
+        version: String,
+    }
+
+    let client = reqwest::Client::new();
+    match client
+        .get(format!("{}/api/version", endpoint.base_url()))
+        .send()
+        .await
+    {
+        Ok(res) => match res.json::<VersionResponse>().await {
+            Ok(body) => ConnectionStatus {
+                available: true,
+                version: Some(body.version),
+            },
+            Err(_) => ConnectionStatus {
+                available: true,
+                version: None,
+            },
+        },
+        Err(_) => ConnectionStatus {
+            available: false,
+            version: None,
+        },
+    }
+}